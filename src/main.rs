@@ -2,26 +2,365 @@ use std::net::{TcpListener, TcpStream};
 use std::io::{Read, Write};
 use std::sync::Arc;
 use std::error::Error;
+use std::thread;
+use std::time::Duration;
 
 use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
 use rustls::pki_types::ServerName;
 use rustls_native_certs::load_native_certs;
 
+/// Number of worker threads handling connections concurrently.
+const WORKER_POOL_SIZE: usize = 64;
+
+/// Read/write timeout applied to both the client and upstream legs of a
+/// proxied HTTP request so a hung peer cannot pin a worker indefinitely.
+/// Not used for CONNECT tunnels once established — see `handle_connect`.
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Timeout for reading just the initial request line/headers, before we
+/// know whether this is a plain request or a CONNECT tunnel. Kept short
+/// since a client is expected to send its header block promptly.
+const HEADER_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum number of bytes buffered while looking for the end of the
+/// request header block. Bounds memory use for a client that never sends
+/// the terminating blank line.
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+
+/// Maximum size of a single decoded chunk in a chunked request body.
+/// Rejects absurd chunk-size lines (e.g. `ffffffffffffffff`) outright
+/// instead of doing arithmetic on them, since `data_start + size` would
+/// otherwise overflow `usize` or index far past the buffer.
+const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+mod cli_config {
+    use std::env;
+
+    /// Knobs gathered from CLI flags / environment variables at startup.
+    /// Grows as new upstream-TLS features need user-facing switches.
+    pub struct ProxyConfig {
+        /// Only offer `http/1.1` via ALPN, even to origins that support h2.
+        pub http1_only: bool,
+        /// Skip upstream certificate verification entirely. Dangerous —
+        /// only meant for developing against self-signed/internal-CA origins.
+        pub insecure: bool,
+        /// Extra PEM bundle of trusted root CAs, added alongside (or instead
+        /// of) the native root store.
+        pub ca_file: Option<String>,
+        /// Don't seed the root store from the OS trust store at all; only
+        /// `ca_file` certificates are trusted.
+        pub no_native_certs: bool,
+        /// PEM certificate chain to present to upstream origins that require
+        /// mutual TLS. Must be paired with `client_key`.
+        pub client_cert: Option<String>,
+        /// PEM private key (PKCS#8, RSA or SEC1) matching `client_cert`.
+        pub client_key: Option<String>,
+    }
+
+    impl ProxyConfig {
+        pub fn from_env() -> Self {
+            let args: Vec<String> = env::args().collect();
+            let http1_only = args.iter().any(|arg| arg == "--http1-only")
+                || env::var("PROX_HTTP1_ONLY").is_ok();
+            let insecure = args.iter().any(|arg| arg == "--insecure")
+                || env::var("PROX_INSECURE").is_ok();
+            let ca_file = args.iter()
+                .position(|arg| arg == "--ca-file")
+                .and_then(|idx| args.get(idx + 1).cloned())
+                .or_else(|| env::var("PROX_CA_FILE").ok());
+            let no_native_certs = args.iter().any(|arg| arg == "--no-native-certs")
+                || env::var("PROX_NO_NATIVE_CERTS").is_ok();
+            let client_cert = args.iter()
+                .position(|arg| arg == "--client-cert")
+                .and_then(|idx| args.get(idx + 1).cloned())
+                .or_else(|| env::var("PROX_CLIENT_CERT").ok());
+            let client_key = args.iter()
+                .position(|arg| arg == "--client-key")
+                .and_then(|idx| args.get(idx + 1).cloned())
+                .or_else(|| env::var("PROX_CLIENT_KEY").ok());
+
+            ProxyConfig {
+                http1_only,
+                insecure,
+                ca_file,
+                no_native_certs,
+                client_cert,
+                client_key,
+            }
+        }
+    }
+}
+
+mod insecure_verifier {
+    use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+    use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use rustls::{DigitallySignedStruct, Error, SignatureScheme};
+
+    /// A `ServerCertVerifier` that accepts any certificate chain the
+    /// upstream presents. Only ever installed when `--insecure`/
+    /// `PROX_INSECURE` is set; never the default.
+    #[derive(Debug)]
+    pub struct NoCertVerification;
+
+    impl ServerCertVerifier for NoCertVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            // Accept anything the peer offers; we never check the signature.
+            vec![
+                SignatureScheme::RSA_PKCS1_SHA256,
+                SignatureScheme::RSA_PKCS1_SHA384,
+                SignatureScheme::RSA_PKCS1_SHA512,
+                SignatureScheme::ECDSA_NISTP256_SHA256,
+                SignatureScheme::ECDSA_NISTP384_SHA384,
+                SignatureScheme::ECDSA_NISTP521_SHA512,
+                SignatureScheme::RSA_PSS_SHA256,
+                SignatureScheme::RSA_PSS_SHA384,
+                SignatureScheme::RSA_PSS_SHA512,
+                SignatureScheme::ED25519,
+            ]
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn accepts_a_certificate_chain_no_real_verifier_would_trust() {
+            let verifier = NoCertVerification;
+            let bogus_cert = CertificateDer::from(vec![0u8; 16]);
+            let server_name = ServerName::try_from("example.com").unwrap();
+
+            let result = verifier.verify_server_cert(&bogus_cert, &[], &server_name, &[], UnixTime::now());
+
+            assert!(result.is_ok());
+        }
+
+        #[test]
+        fn advertises_schemes_it_never_actually_checks() {
+            let verifier = NoCertVerification;
+            assert!(!verifier.supported_verify_schemes().is_empty());
+        }
+    }
+}
+
 mod tls_config {
     use super::*;
+    use cli_config::ProxyConfig;
+    use insecure_verifier::NoCertVerification;
+
+    /// Load a PEM certificate chain for presenting to upstream origins.
+    fn load_cert_chain(cert_file: &str) -> Result<Vec<rustls::pki_types::CertificateDer<'static>>, Box<dyn Error>> {
+        let mut reader = std::io::BufReader::new(std::fs::File::open(cert_file)?);
+        let certs = rustls_pemfile::certs(&mut reader).collect::<Result<Vec<_>, _>>()?;
+        Ok(certs)
+    }
+
+    /// Load a PKCS#8/RSA/SEC1 PEM private key matching a client cert chain.
+    fn load_private_key(key_file: &str) -> Result<rustls::pki_types::PrivateKeyDer<'static>, Box<dyn Error>> {
+        let mut reader = std::io::BufReader::new(std::fs::File::open(key_file)?);
+        rustls_pemfile::private_key(&mut reader)?.ok_or_else(|| "no private key found in client key file".into())
+    }
+
+    pub fn build(config: &ProxyConfig) -> Result<ClientConfig, Box<dyn Error>> {
+        let builder = if config.insecure {
+            eprintln!("WARNING: --insecure set, upstream certificate verification is DISABLED");
+            ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoCertVerification))
+        } else {
+            let mut root_store = RootCertStore::empty();
+
+            if !config.no_native_certs {
+                let native_certs = load_native_certs().certs;
+                for cert in native_certs {
+                    root_store.add(cert)?;
+                }
+            }
+
+            if let Some(ca_file) = &config.ca_file {
+                let mut reader = std::io::BufReader::new(std::fs::File::open(ca_file)?);
+                for cert in rustls_pemfile::certs(&mut reader) {
+                    root_store.add(cert?)?;
+                }
+            }
+
+            ClientConfig::builder().with_root_certificates(root_store)
+        };
+
+        let mut tls_config = match (&config.client_cert, &config.client_key) {
+            (Some(cert_file), Some(key_file)) => {
+                let cert_chain = load_cert_chain(cert_file)
+                    .map_err(|e| format!("failed to load --client-cert {cert_file}: {e}"))?;
+                let key = load_private_key(key_file)
+                    .map_err(|e| format!("failed to load --client-key {key_file}: {e}"))?;
+                builder.with_client_auth_cert(cert_chain, key)?
+            }
+            (None, None) => builder.with_no_client_auth(),
+            _ => return Err("--client-cert and --client-key must be set together".into()),
+        };
+
+        tls_config.alpn_protocols = if config.http1_only {
+            vec![b"http/1.1".to_vec()]
+        } else {
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        };
+
+        if std::env::var_os("SSLKEYLOGFILE").is_some() {
+            tls_config.key_log = Arc::new(rustls::KeyLogFile::new());
+        }
+
+        Ok(tls_config)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // `insecure: true` skips loading the OS trust store, so tests that
+        // keep this default don't touch root-certificate loading; the CA-file
+        // tests below switch it off.
+        fn config(http1_only: bool) -> ProxyConfig {
+            ProxyConfig {
+                http1_only,
+                insecure: true,
+                ca_file: None,
+                no_native_certs: false,
+                client_cert: None,
+                client_key: None,
+            }
+        }
+
+        #[test]
+        fn offers_h2_and_http1_by_default() {
+            let tls_config = build(&config(false)).unwrap();
+            assert_eq!(tls_config.alpn_protocols, vec![b"h2".to_vec(), b"http/1.1".to_vec()]);
+        }
+
+        #[test]
+        fn http1_only_knob_drops_h2_from_alpn() {
+            let tls_config = build(&config(true)).unwrap();
+            assert_eq!(tls_config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+        }
+
+        #[test]
+        fn sslkeylogfile_swaps_in_a_logging_key_log() {
+            // `rustls::KeyLogFile` always answers `will_log` with its
+            // default (`true`); the no-op `ClientConfig` default answers
+            // `false`. That's the only public signal this knob leaves
+            // behind, since the logger itself just appends to a file.
+            unsafe { std::env::remove_var("SSLKEYLOGFILE") };
+            let without_env = build(&config(false)).unwrap();
+            assert!(!without_env.key_log.will_log("CLIENT_RANDOM"));
+
+            let log_file = std::env::temp_dir().join("prox-test-sslkeylogfile");
+            unsafe { std::env::set_var("SSLKEYLOGFILE", &log_file) };
+            let with_env = build(&config(false)).unwrap();
+            unsafe { std::env::remove_var("SSLKEYLOGFILE") };
+            let _ = std::fs::remove_file(&log_file);
+
+            assert!(with_env.key_log.will_log("CLIENT_RANDOM"));
+        }
+
+        // A throwaway self-signed cert/key pair, used only to exercise the
+        // PEM-loading paths below; it's never presented to a real server.
+        const TEST_CERT_PEM: &str = include_str!("../testdata/test-cert.pem");
+        const TEST_KEY_PEM: &str = include_str!("../testdata/test-key.pem");
+
+        fn write_temp_file(name: &str, contents: &str) -> std::path::PathBuf {
+            let path = std::env::temp_dir().join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        }
+
+        #[test]
+        fn loads_extra_trusted_roots_from_a_ca_file() {
+            let ca_file = write_temp_file("prox-test-ca-file.pem", TEST_CERT_PEM);
+
+            let mut config = config(false);
+            config.insecure = false;
+            config.no_native_certs = true;
+            config.ca_file = Some(ca_file.to_str().unwrap().to_string());
 
-    pub fn build() -> Result<ClientConfig, Box<dyn Error>> {
-        let mut root_store = RootCertStore::empty();
-        let native_certs = load_native_certs().certs;
-        for cert in native_certs {
-            root_store.add(cert)?;
+            let result = build(&config);
+            let _ = std::fs::remove_file(&ca_file);
+
+            assert!(result.is_ok());
+        }
+
+        #[test]
+        fn a_ca_file_that_does_not_exist_is_an_error() {
+            let mut config = config(false);
+            config.insecure = false;
+            config.no_native_certs = true;
+            config.ca_file = Some("/nonexistent/prox-test-ca-file.pem".to_string());
+
+            assert!(build(&config).is_err());
+        }
+
+        #[test]
+        fn client_cert_without_client_key_is_an_error() {
+            let mut config = config(false);
+            config.client_cert = Some("/nonexistent/cert.pem".to_string());
+            config.client_key = None;
+
+            let err = build(&config).unwrap_err();
+            assert_eq!(err.to_string(), "--client-cert and --client-key must be set together");
+        }
+
+        #[test]
+        fn client_key_without_client_cert_is_an_error() {
+            let mut config = config(false);
+            config.client_cert = None;
+            config.client_key = Some("/nonexistent/key.pem".to_string());
+
+            let err = build(&config).unwrap_err();
+            assert_eq!(err.to_string(), "--client-cert and --client-key must be set together");
         }
 
-        let config = ClientConfig::builder()
-            .with_root_certificates(root_store)
-            .with_no_client_auth();
+        #[test]
+        fn loads_a_matching_client_cert_and_key_pair() {
+            let cert_file = write_temp_file("prox-test-client-cert.pem", TEST_CERT_PEM);
+            let key_file = write_temp_file("prox-test-client-key.pem", TEST_KEY_PEM);
+
+            let mut config = config(false);
+            config.client_cert = Some(cert_file.to_str().unwrap().to_string());
+            config.client_key = Some(key_file.to_str().unwrap().to_string());
+
+            let result = build(&config);
+            let _ = std::fs::remove_file(&cert_file);
+            let _ = std::fs::remove_file(&key_file);
 
-        Ok(config)
+            assert!(result.is_ok());
+        }
     }
 }
 
@@ -32,6 +371,218 @@ mod http_parser {
             .ok_or("Missing Host header")?;
         Ok(host_line.trim_start_matches("Host:").trim().to_string())
     }
+
+    /// Rebuild a raw header block with `Connection: close`, replacing an
+    /// existing `Connection` header if present or appending one otherwise.
+    /// All other headers (including `Host`) are left untouched and in order.
+    pub fn rewrite_connection_close(headers: &str) -> String {
+        let mut saw_connection = false;
+        let mut lines: Vec<String> = headers
+            .lines()
+            .map(|line| {
+                if line.to_lowercase().starts_with("connection:") {
+                    saw_connection = true;
+                    "Connection: close".to_string()
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect();
+
+        if !saw_connection {
+            lines.push("Connection: close".to_string());
+        }
+
+        lines.join("\r\n")
+    }
+
+    /// Rebuild a raw header block for forwarding a request whose body has
+    /// already been fully read off the wire and decoded into `body_len`
+    /// flat bytes. Drops `Transfer-Encoding` (the body is no longer
+    /// chunk-framed) and any existing `Content-Length` (which described the
+    /// original framing, not the decoded length), then appends a fresh
+    /// `Content-Length: {body_len}`.
+    pub fn rewrite_framing_headers(headers: &str, body_len: usize) -> String {
+        let mut lines: Vec<String> = headers
+            .lines()
+            .filter(|line| {
+                let lower = line.to_lowercase();
+                !lower.starts_with("transfer-encoding:") && !lower.starts_with("content-length:")
+            })
+            .map(|line| line.to_string())
+            .collect();
+
+        lines.push(format!("Content-Length: {body_len}"));
+        lines.join("\r\n")
+    }
+
+    pub fn content_length(headers: &str) -> Option<usize> {
+        headers.lines()
+            .find(|line| line.to_lowercase().starts_with("content-length:"))
+            .and_then(|line| line.split_once(':').map(|(_, value)| value))
+            .and_then(|value| value.trim().parse().ok())
+    }
+
+    fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack.windows(needle.len()).position(|window| window == needle)
+    }
+
+    /// Try to decode a complete chunked-encoded body out of `raw`, the raw
+    /// (still chunk-framed) bytes read from the client so far. Returns
+    /// `Ok(None)` if `raw` doesn't yet hold a full body (more bytes need to
+    /// be read and appended), `Ok(Some(body))` once the terminating
+    /// zero-length chunk and any trailer headers have been consumed, or
+    /// `Err` if a chunk-size line is malformed.
+    ///
+    /// Parses real chunk framing (hex size + CRLF, that many data bytes,
+    /// CRLF, repeat) rather than scanning for the literal `0\r\n\r\n`
+    /// terminator, which would falsely trigger on chunk data that happens
+    /// to contain that byte sequence.
+    pub fn decode_chunked_body(raw: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        let mut body = Vec::new();
+        let mut pos = 0;
+
+        loop {
+            let size_line_end = match find_subslice(&raw[pos..], b"\r\n") {
+                Some(idx) => pos + idx,
+                None => return Ok(None),
+            };
+            let size_line = std::str::from_utf8(&raw[pos..size_line_end])?;
+            let size_str = size_line.split(';').next().unwrap_or("").trim();
+            let size = usize::from_str_radix(size_str, 16)
+                .map_err(|_| format!("invalid chunk size {size_str:?}"))?;
+            if size > super::MAX_CHUNK_SIZE {
+                return Err(format!("chunk size {size} exceeds the {}-byte maximum", super::MAX_CHUNK_SIZE).into());
+            }
+            let data_start = size_line_end + 2;
+
+            if size == 0 {
+                // The zero-length chunk's own CRLF (already at size_line_end)
+                // is the first half of the terminator: with no trailers the
+                // next bytes are just one more CRLF, giving the familiar
+                // "0\r\n\r\n"; with trailers, each trailer line's CRLF is
+                // followed by a final blank-line CRLF, so the same
+                // "\r\n\r\n" search still finds the true end.
+                return match find_subslice(&raw[size_line_end..], b"\r\n\r\n") {
+                    Some(_) => Ok(Some(body)),
+                    None => Ok(None),
+                };
+            }
+
+            let data_end = data_start.checked_add(size).ok_or("chunk size overflows buffer offset")?;
+            if raw.len() < data_end + 2 {
+                return Ok(None);
+            }
+            body.extend_from_slice(&raw[data_start..data_end]);
+            pos = data_end + 2;
+        }
+    }
+
+    pub fn is_chunked(headers: &str) -> bool {
+        headers.lines()
+            .find(|line| line.to_lowercase().starts_with("transfer-encoding:"))
+            .map(|line| line.to_lowercase().contains("chunked"))
+            .unwrap_or(false)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn extracts_host_header() {
+            assert_eq!(extract_host("GET / HTTP/1.1\r\nHost: example.com").unwrap(), "example.com");
+        }
+
+        #[test]
+        fn missing_host_header_is_an_error() {
+            assert!(extract_host("GET / HTTP/1.1").is_err());
+        }
+
+        #[test]
+        fn rewrite_replaces_existing_connection_header() {
+            let out = rewrite_connection_close("Host: example.com\r\nConnection: keep-alive");
+            assert_eq!(out, "Host: example.com\r\nConnection: close");
+        }
+
+        #[test]
+        fn rewrite_appends_connection_header_when_absent() {
+            let out = rewrite_connection_close("Host: example.com");
+            assert_eq!(out, "Host: example.com\r\nConnection: close");
+        }
+
+        #[test]
+        fn framing_headers_replace_transfer_encoding_with_content_length() {
+            let out = rewrite_framing_headers(
+                "POST /upload HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked",
+                9,
+            );
+            assert_eq!(out, "POST /upload HTTP/1.1\r\nHost: example.com\r\nContent-Length: 9");
+        }
+
+        #[test]
+        fn framing_headers_drop_a_stale_content_length_too() {
+            let out = rewrite_framing_headers(
+                "Host: example.com\r\nContent-Length: 999\r\nTransfer-Encoding: chunked",
+                3,
+            );
+            assert_eq!(out, "Host: example.com\r\nContent-Length: 3");
+        }
+
+        #[test]
+        fn parses_content_length() {
+            assert_eq!(content_length("Content-Length: 42"), Some(42));
+            assert_eq!(content_length("Host: example.com"), None);
+        }
+
+        #[test]
+        fn detects_chunked_transfer_encoding() {
+            assert!(is_chunked("Transfer-Encoding: chunked"));
+            assert!(!is_chunked("Transfer-Encoding: gzip"));
+            assert!(!is_chunked("Host: example.com"));
+        }
+
+        #[test]
+        fn decodes_a_single_chunk_body() {
+            let raw = b"5\r\nhello\r\n0\r\n\r\n";
+            assert_eq!(decode_chunked_body(raw).unwrap(), Some(b"hello".to_vec()));
+        }
+
+        #[test]
+        fn decodes_multiple_chunks_and_trailers() {
+            let raw = b"4\r\nWiki\r\n5\r\npedia\r\n0\r\nX-Trailer: yes\r\n\r\n";
+            assert_eq!(decode_chunked_body(raw).unwrap(), Some(b"Wikipedia".to_vec()));
+        }
+
+        #[test]
+        fn returns_none_while_more_data_is_needed() {
+            // No terminating zero-length chunk yet.
+            assert_eq!(decode_chunked_body(b"5\r\nhello\r\n").unwrap(), None);
+            // Chunk-size line itself hasn't arrived yet.
+            assert_eq!(decode_chunked_body(b"5").unwrap(), None);
+        }
+
+        #[test]
+        fn chunk_data_containing_the_terminator_bytes_is_not_mistaken_for_eof() {
+            // The payload's data deliberately contains the literal bytes
+            // "0\r\n\r\n" — a naive string search for that terminator would
+            // stop here, truncating the body before the real final chunk.
+            let raw = b"5\r\n0\r\n\r\n\r\n3\r\nend\r\n0\r\n\r\n";
+            assert_eq!(decode_chunked_body(raw).unwrap(), Some(b"0\r\n\r\nend".to_vec()));
+        }
+
+        #[test]
+        fn rejects_a_malformed_chunk_size() {
+            assert!(decode_chunked_body(b"not-hex\r\nhello\r\n").is_err());
+        }
+
+        #[test]
+        fn rejects_an_oversized_chunk_size_instead_of_overflowing() {
+            // Used to panic with "attempt to add with overflow" (debug) or
+            // a slice-index panic (release) instead of erroring cleanly.
+            assert!(decode_chunked_body(b"ffffffffffffffff\r\n").is_err());
+        }
+    }
 }
 
 mod tls_connector {
@@ -41,6 +592,8 @@ mod tls_connector {
         let server_name = ServerName::try_from(host.clone())?;
         let conn = ClientConnection::new(config, server_name)?;
         let tls_socket = TcpStream::connect((host.as_str(), 443))?;
+        tls_socket.set_read_timeout(Some(IO_TIMEOUT))?;
+        tls_socket.set_write_timeout(Some(IO_TIMEOUT))?;
         Ok(StreamOwned::new(conn, tls_socket))
     }
 }
@@ -48,12 +601,11 @@ mod tls_connector {
 mod http_forwarder {
     use super::*;
 
-    pub fn forward_request(stream: &mut StreamOwned<ClientConnection, TcpStream>, host: &str) -> Result<Vec<u8>, Box<dyn Error>> {
-        let request = format!(
-            "GET / HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
-            host
-        );
-        stream.write_all(request.as_bytes())?;
+    pub fn forward_request(stream: &mut StreamOwned<ClientConnection, TcpStream>, request_head: &str, body: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        stream.write_all(request_head.as_bytes())?;
+        if !body.is_empty() {
+            stream.write_all(body)?;
+        }
 
         let mut response = Vec::new();
         stream.read_to_end(&mut response)?;
@@ -61,35 +613,564 @@ mod http_forwarder {
     }
 }
 
+mod http2_pool {
+    use super::*;
+    use bytes::Bytes;
+    use h2::client::{self, SendRequest};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use tokio_rustls::TlsConnector;
+
+    /// Long-lived per-host HTTP/2 connections, reused across requests.
+    /// `h2::client::SendRequest` is cheaply `Clone` and designed for
+    /// concurrent streams to be issued from clones of the same handle, so
+    /// one handshake per host backs any number of proxied requests instead
+    /// of paying a fresh TLS + h2 handshake (and a new tokio runtime) on
+    /// every call.
+    pub struct Http2Pool {
+        runtime: tokio::runtime::Runtime,
+        connections: Mutex<HashMap<String, SendRequest<Bytes>>>,
+    }
+
+    impl Http2Pool {
+        pub fn new() -> Result<Self, Box<dyn Error>> {
+            Ok(Http2Pool {
+                runtime: tokio::runtime::Runtime::new()?,
+                connections: Mutex::new(HashMap::new()),
+            })
+        }
+
+        /// Whether a connection for `host` is already cached. Lets callers
+        /// skip the sync ALPN-probe handshake entirely once a host is known
+        /// to speak h2.
+        pub fn has_connection(&self, host: &str) -> bool {
+            self.connections.lock().unwrap().contains_key(host)
+        }
+
+        fn cached(&self, host: &str) -> Option<SendRequest<Bytes>> {
+            self.connections.lock().unwrap().get(host).cloned()
+        }
+
+        async fn connect(&self, host: &str, tls_config: Arc<ClientConfig>) -> Result<SendRequest<Bytes>, Box<dyn Error>> {
+            let tcp = tokio::time::timeout(IO_TIMEOUT, tokio::net::TcpStream::connect((host, 443))).await??;
+            let connector = TlsConnector::from(tls_config);
+            let server_name = ServerName::try_from(host.to_string())?.to_owned();
+            let tls_stream = tokio::time::timeout(IO_TIMEOUT, connector.connect(server_name, tcp)).await??;
+
+            let (h2_client, connection) = tokio::time::timeout(IO_TIMEOUT, client::handshake(tls_stream)).await??;
+            tokio::spawn(async move {
+                let _ = connection.await;
+            });
+
+            self.connections.lock().unwrap().insert(host.to_string(), h2_client.clone());
+            Ok(h2_client)
+        }
+
+        /// Get a connection handle for `host`, reusing the cached one if
+        /// present, dialing a fresh one otherwise.
+        async fn get(&self, host: &str, tls_config: Arc<ClientConfig>) -> Result<SendRequest<Bytes>, Box<dyn Error>> {
+            match self.cached(host) {
+                Some(conn) => Ok(conn),
+                None => self.connect(host, tls_config).await,
+            }
+        }
+
+        pub fn block_on<F: std::future::Future>(&self, fut: F) -> F::Output {
+            self.runtime.block_on(fut)
+        }
+    }
+
+    async fn send(
+        h2_client: &mut SendRequest<Bytes>,
+        method: &str,
+        path: &str,
+        headers: &str,
+        body: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        *h2_client = tokio::time::timeout(IO_TIMEOUT, h2_client.clone().ready()).await??;
+
+        let mut request_builder = http::Request::builder().method(method).uri(path);
+        for line in headers.lines().skip(1) {
+            if let Some((name, value)) = line.split_once(':') {
+                request_builder = request_builder.header(name.trim(), value.trim());
+            }
+        }
+        let request = request_builder.body(())?;
+
+        let (response, mut send_stream) = h2_client.send_request(request, body.is_empty())?;
+        if !body.is_empty() {
+            send_stream.send_data(Bytes::copy_from_slice(body), true)?;
+        }
+
+        let response = tokio::time::timeout(IO_TIMEOUT, response).await??;
+        let mut out = format!("HTTP/1.1 {}\r\n", response.status()).into_bytes();
+        for (name, value) in response.headers() {
+            out.extend_from_slice(name.as_str().as_bytes());
+            out.extend_from_slice(b": ");
+            out.extend_from_slice(value.as_bytes());
+            out.extend_from_slice(b"\r\n");
+        }
+        out.extend_from_slice(b"\r\n");
+
+        let mut body_stream = response.into_body();
+        while let Some(chunk) = tokio::time::timeout(IO_TIMEOUT, body_stream.data()).await? {
+            out.extend_from_slice(&chunk?);
+        }
+
+        Ok(out)
+    }
+
+    /// Drive the upstream leg over HTTP/2 for origins that negotiated `h2`
+    /// via ALPN, reusing a cached per-host connection when one is already
+    /// open and serializing the response back into an HTTP/1.1-style byte
+    /// stream so callers can treat it the same as the `http_forwarder` path.
+    pub fn forward_request(
+        pool: &Http2Pool,
+        host: &str,
+        tls_config: Arc<ClientConfig>,
+        method: &str,
+        path: &str,
+        headers: &str,
+        body: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        pool.block_on(async {
+            let mut h2_client = pool.get(host, tls_config.clone()).await?;
+            match send(&mut h2_client, method, path, headers, body).await {
+                Ok(response) => Ok(response),
+                Err(_) => {
+                    // The cached connection was dead (idle-closed by the
+                    // origin, network blip, …); dial once more before
+                    // giving up.
+                    let mut h2_client = pool.connect(host, tls_config).await?;
+                    send(&mut h2_client, method, path, headers, body).await
+                }
+            }
+        })
+    }
+}
+
+mod connect_tunnel {
+    use super::*;
+
+    /// Pull the `host:port` authority out of a `CONNECT host:port HTTP/1.1`
+    /// request line.
+    pub fn parse_authority(first_line: &str) -> Result<&str, Box<dyn Error>> {
+        first_line
+            .split_whitespace()
+            .nth(1)
+            .ok_or_else(|| "Malformed CONNECT request line".into())
+    }
+
+    /// Splice `client_stream` and `upstream` together until either side closes.
+    /// Runs one direction on the current thread and the other on a spawned
+    /// thread so both legs can make progress independently. When either
+    /// direction ends, its sockets are shut down so the other one ends too.
+    pub fn splice(client_stream: TcpStream, upstream: TcpStream) -> Result<(), Box<dyn Error>> {
+        let mut client_reader = client_stream.try_clone()?;
+        let mut upstream_writer = upstream.try_clone()?;
+
+        let uploader = thread::spawn(move || {
+            let _ = std::io::copy(&mut client_reader, &mut upstream_writer);
+            let _ = upstream_writer.shutdown(std::net::Shutdown::Both);
+        });
+
+        let mut upstream_reader = upstream;
+        let mut client_writer = client_stream;
+        let _ = std::io::copy(&mut upstream_reader, &mut client_writer);
+        let _ = client_writer.shutdown(std::net::Shutdown::Both);
+
+        let _ = uploader.join();
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn parses_host_and_port() {
+            assert_eq!(parse_authority("CONNECT example.com:443 HTTP/1.1").unwrap(), "example.com:443");
+        }
+
+        #[test]
+        fn rejects_a_line_with_no_authority() {
+            assert!(parse_authority("CONNECT").is_err());
+        }
+
+        #[test]
+        fn rejects_an_empty_line() {
+            assert!(parse_authority("").is_err());
+        }
+    }
+}
+
+mod thread_pool {
+    use super::*;
+    use std::sync::mpsc;
+    use std::sync::Mutex;
+
+    type Job = Box<dyn FnOnce() + Send + 'static>;
+
+    /// A fixed-size pool of worker threads pulling jobs off a bounded
+    /// channel. Submitting a job blocks once the channel is full, which
+    /// turns an overloaded pool into backpressure on the accept loop
+    /// instead of unbounded thread spawning.
+    pub struct ThreadPool {
+        sender: mpsc::SyncSender<Job>,
+    }
+
+    impl ThreadPool {
+        pub fn new(size: usize) -> ThreadPool {
+            let (sender, receiver) = mpsc::sync_channel::<Job>(size);
+            let receiver = Arc::new(Mutex::new(receiver));
+
+            for _ in 0..size {
+                let receiver = Arc::clone(&receiver);
+                thread::spawn(move || loop {
+                    let job = receiver.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                });
+            }
+
+            ThreadPool { sender }
+        }
+
+        pub fn execute<F>(&self, job: F)
+        where
+            F: FnOnce() + Send + 'static,
+        {
+            let _ = self.sender.send(Box::new(job));
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::sync::mpsc::channel;
+
+        #[test]
+        fn runs_every_submitted_job() {
+            let pool = ThreadPool::new(4);
+            let (done_tx, done_rx) = channel();
+
+            for i in 0..16 {
+                let done_tx = done_tx.clone();
+                pool.execute(move || {
+                    let _ = done_tx.send(i);
+                });
+            }
+            drop(done_tx);
+
+            let mut results: Vec<i32> = done_rx.iter().collect();
+            results.sort_unstable();
+            assert_eq!(results, (0..16).collect::<Vec<_>>());
+        }
+
+        #[test]
+        fn runs_jobs_on_more_than_one_thread() {
+            // A Barrier forces all 4 jobs to be mid-flight at once, so the
+            // pool can only clear it if it actually dispatched them to
+            // more than one worker thread concurrently.
+            let pool = ThreadPool::new(4);
+            let barrier = Arc::new(std::sync::Barrier::new(4));
+            let (tid_tx, tid_rx) = channel();
+
+            for _ in 0..4 {
+                let tid_tx = tid_tx.clone();
+                let barrier = Arc::clone(&barrier);
+                pool.execute(move || {
+                    barrier.wait();
+                    let _ = tid_tx.send(thread::current().id());
+                });
+            }
+            drop(tid_tx);
+
+            let seen: std::collections::HashSet<_> = tid_rx.iter().collect();
+            assert_eq!(seen.len(), 4, "expected all 4 jobs to run concurrently on distinct worker threads");
+        }
+    }
+}
+
 mod proxy_handler {
     use super::*;
 
-    pub fn handle_client(mut client_stream: TcpStream, tls_config: Arc<ClientConfig>) -> Result<(), Box<dyn Error>> {
-        let mut buffer = [0; 4096];
-        let n = client_stream.read(&mut buffer)?;
-        let request_str = String::from_utf8_lossy(&buffer[..n]).to_string();
+    fn handle_connect(mut client_stream: TcpStream, first_line: &str, leftover: &[u8]) -> Result<(), Box<dyn Error>> {
+        let authority = connect_tunnel::parse_authority(first_line)?;
+
+        let mut upstream = match TcpStream::connect(authority) {
+            Ok(stream) => stream,
+            Err(_) => {
+                client_stream.write_all(b"HTTP/1.1 502 Bad Gateway\r\n\r\n")?;
+                return Ok(());
+            }
+        };
+
+        client_stream.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")?;
+
+        if !leftover.is_empty() {
+            upstream.write_all(leftover)?;
+        }
+
+        // Once tunneled, the client owns this connection end-to-end and
+        // traffic may legitimately sit idle far longer than
+        // HEADER_READ_TIMEOUT (keep-alive, SSE, websocket heartbeats). Clear
+        // the read/write deadlines inherited from `handle_client` rather
+        // than splicing under a flat socket timeout that would tear the
+        // tunnel down on any idle gap.
+        client_stream.set_read_timeout(None)?;
+        client_stream.set_write_timeout(None)?;
+        upstream.set_read_timeout(None)?;
+        upstream.set_write_timeout(None)?;
+
+        connect_tunnel::splice(client_stream, upstream)
+    }
+
+    fn find_header_end(buffer: &[u8]) -> Option<usize> {
+        buffer.windows(4).position(|window| window == b"\r\n\r\n").map(|idx| idx + 4)
+    }
+
+    /// Read from `stream` into `buffer`, growing it as needed, until a
+    /// blank line (`\r\n\r\n`) terminating the request header block is
+    /// seen. Returns the offset of the first byte past that blank line;
+    /// anything already read beyond it is left in `buffer` as body
+    /// leftover. Generic over `Read` so it can be exercised with an
+    /// in-memory reader in tests, unlike the rest of this module which
+    /// talks to a concrete `TcpStream`.
+    fn read_headers<R: Read>(stream: &mut R, buffer: &mut Vec<u8>) -> Result<usize, Box<dyn Error>> {
+        let mut chunk = [0u8; 4096];
+        loop {
+            if let Some(header_end) = find_header_end(buffer) {
+                return Ok(header_end);
+            }
+            if buffer.len() >= MAX_HEADER_SIZE {
+                return Err("request header too large".into());
+            }
+            let n = stream.read(&mut chunk)?;
+            if n == 0 {
+                return Err("client closed connection before request headers completed".into());
+            }
+            buffer.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Read the client's request body, honoring `Content-Length` or
+    /// `Transfer-Encoding: chunked`. `leftover` is whatever body bytes were
+    /// already read into the initial header buffer and must be forwarded first.
+    /// Per RFC 7230 §3.3.3: if `Transfer-Encoding: chunked` is present it
+    /// takes priority over any `Content-Length` on the same request,
+    /// regardless of which header came first — an ambiguous request
+    /// carrying both must not be framed by Content-Length, or a proxy and
+    /// the origin can disagree about where the request ends (request
+    /// smuggling).
+    fn read_body<R: Read>(client_stream: &mut R, leftover: &[u8], content_length: Option<usize>, chunked: bool) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut body = leftover.to_vec();
+
+        if chunked {
+            let mut buf = [0u8; 4096];
+            loop {
+                if let Some(decoded) = http_parser::decode_chunked_body(&body)? {
+                    body = decoded;
+                    break;
+                }
+                let n = client_stream.read(&mut buf)?;
+                if n == 0 {
+                    return Err("client closed connection before chunked body completed".into());
+                }
+                body.extend_from_slice(&buf[..n]);
+            }
+        } else if let Some(len) = content_length {
+            let mut buf = [0u8; 4096];
+            while body.len() < len {
+                let n = client_stream.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                body.extend_from_slice(&buf[..n]);
+            }
+            body.truncate(len.min(body.len()));
+        }
+
+        Ok(body)
+    }
+
+    pub fn handle_client(mut client_stream: TcpStream, tls_config: Arc<ClientConfig>, http2_pool: Arc<http2_pool::Http2Pool>) -> Result<(), Box<dyn Error>> {
+        client_stream.set_read_timeout(Some(HEADER_READ_TIMEOUT))?;
+        client_stream.set_write_timeout(Some(HEADER_READ_TIMEOUT))?;
+
+        let mut buffer = Vec::with_capacity(4096);
+        let header_end = read_headers(&mut client_stream, &mut buffer)?;
+        let request_str = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+
+        let first_line = request_str.lines().next().unwrap_or("");
+        let leftover = buffer[header_end..].to_vec();
+
+        if first_line.to_uppercase().starts_with("CONNECT ") {
+            return handle_connect(client_stream, first_line, &leftover);
+        }
+
+        // Past the header-read deadline, a plain proxied request switches
+        // to the longer steady-state timeout for the rest of the
+        // request/response round trip (it doesn't tunnel, so it doesn't
+        // need the unbounded lifetime CONNECT does).
+        client_stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        client_stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+        let headers = request_str.trim_end_matches("\r\n\r\n");
+        let host = http_parser::extract_host(headers)?;
+
+        let content_length = http_parser::content_length(headers);
+        let chunked = http_parser::is_chunked(headers);
+        let body = read_body(&mut client_stream, &leftover, content_length, chunked)?;
 
-        let host = http_parser::extract_host(&request_str)?;
-        let mut tls_stream = tls_connector::connect(host.clone(), tls_config)?;
-        let response = http_forwarder::forward_request(&mut tls_stream, &host)?;
+        // read_body already de-chunks a chunked request body into flat
+        // bytes, so the headers we forward must no longer claim
+        // Transfer-Encoding: chunked — they need a Content-Length matching
+        // what actually got decoded, or the origin reads a chunk-framed
+        // body out of a request that isn't chunk-framed on the wire.
+        let headers = if chunked {
+            http_parser::rewrite_framing_headers(headers, body.len())
+        } else {
+            headers.to_string()
+        };
+        let headers = headers.as_str();
+
+        let rewritten_headers = http_parser::rewrite_connection_close(headers);
+        let request_head = format!("{}\r\n\r\n", rewritten_headers);
+
+        // A host already known to speak h2 skips the sync TLS handshake
+        // below entirely and reuses its cached connection; only a host
+        // we haven't talked to yet needs the ALPN probe.
+        let response = if http2_pool.has_connection(&host) {
+            let method = first_line.split_whitespace().next().unwrap_or("GET");
+            let path = first_line.split_whitespace().nth(1).unwrap_or("/");
+            http2_pool::forward_request(&http2_pool, &host, tls_config, method, path, headers, &body)?
+        } else {
+            // First request to a host we have no cached h2 connection for:
+            // this sync handshake learns the negotiated ALPN protocol. For
+            // http/1.1 the request is sent over it; if it turns out to be
+            // h2, it is thrown away and `http2_pool::forward_request` below dials its
+            // own async TLS + h2 handshake rather than reusing this one, so
+            // the very first request to every new h2 host still pays two
+            // handshakes. Every request after that reuses the cached
+            // connection and pays for neither — it's only this cold-start
+            // ALPN probe that's unavoidable without threading a sync
+            // `StreamOwned` into the async h2 client.
+            let mut tls_stream = tls_connector::connect(host.clone(), tls_config.clone())?;
+            tls_stream.conn.complete_io(&mut tls_stream.sock)?;
+
+            if tls_stream.conn.alpn_protocol() == Some(b"h2") {
+                let method = first_line.split_whitespace().next().unwrap_or("GET");
+                let path = first_line.split_whitespace().nth(1).unwrap_or("/");
+                http2_pool::forward_request(&http2_pool, &host, tls_config, method, path, headers, &body)?
+            } else {
+                http_forwarder::forward_request(&mut tls_stream, &request_head, &body)?
+            }
+        };
 
         client_stream.write_all(&response)?;
         Ok(())
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::io::Cursor;
+
+        #[test]
+        fn splits_header_block_from_leftover_body_bytes() {
+            // Cursor hands back whatever's in the buffer in one `read`, so
+            // the header block and the first body bytes arrive together.
+            let mut source = Cursor::new(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\nbody".to_vec());
+            let mut buffer = Vec::new();
+            let header_end = read_headers(&mut source, &mut buffer).unwrap();
+            assert_eq!(&buffer[..header_end], b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n");
+            assert_eq!(&buffer[header_end..], b"body");
+        }
+
+        #[test]
+        fn keeps_reading_past_an_initial_chunk_with_no_blank_line() {
+            struct TwoPartReader {
+                parts: Vec<Vec<u8>>,
+            }
+
+            impl Read for TwoPartReader {
+                fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                    if self.parts.is_empty() {
+                        return Ok(0);
+                    }
+                    let part = self.parts.remove(0);
+                    buf[..part.len()].copy_from_slice(&part);
+                    Ok(part.len())
+                }
+            }
+
+            // Simulate a header block bigger than a single 4096-byte read:
+            // the first read has no "\r\n\r\n" anywhere in it at all.
+            let mut source = TwoPartReader {
+                parts: vec![b"GET / HTTP/1.1\r\nX-Pad: aaaa".to_vec(), b"\r\n\r\n".to_vec()],
+            };
+            let mut buffer = Vec::new();
+            let header_end = read_headers(&mut source, &mut buffer).unwrap();
+            assert_eq!(header_end, buffer.len());
+        }
+
+        #[test]
+        fn gives_up_once_headers_exceed_the_size_bound() {
+            struct InfiniteReader;
+
+            impl Read for InfiniteReader {
+                fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                    buf.fill(b'a');
+                    Ok(buf.len())
+                }
+            }
+
+            let mut buffer = Vec::new();
+            assert!(read_headers(&mut InfiniteReader, &mut buffer).is_err());
+        }
+
+        #[test]
+        fn chunked_body_is_decoded_even_when_leftover_held_raw_chunk_bytes() {
+            let mut rest = Cursor::new(b"hello\r\n0\r\n\r\n".to_vec());
+            let body = read_body(&mut rest, b"5\r\n", None, true).unwrap();
+            assert_eq!(body, b"hello");
+        }
+
+        #[test]
+        fn chunked_wins_over_content_length_when_a_request_carries_both() {
+            // Per RFC 7230 Transfer-Encoding: chunked must take priority
+            // over Content-Length on the same request; with only 5 bytes to
+            // give, Content-Length-first framing would stop at EOF and hand
+            // back the raw "0\r\n\r\n" as the body.
+            let mut rest = Cursor::new(b"0\r\n\r\n".to_vec());
+            let body = read_body(&mut rest, b"", Some(999), true).unwrap();
+            assert_eq!(body, b"");
+        }
+    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let listener = TcpListener::bind("127.0.0.1:8080")?;
-    println!("Synchronous TLS proxy listening on http://127.0.0.1:8080");
+    println!("TLS proxy listening on http://127.0.0.1:8080");
 
-    let tls_config = Arc::new(tls_config::build()?);
+    let config = cli_config::ProxyConfig::from_env();
+    let tls_config = Arc::new(tls_config::build(&config)?);
+    let http2_pool = Arc::new(http2_pool::Http2Pool::new()?);
+    let pool = thread_pool::ThreadPool::new(WORKER_POOL_SIZE);
 
     for stream in listener.incoming() {
         match stream {
             Ok(client_stream) => {
-                if let Err(e) = proxy_handler::handle_client(client_stream, tls_config.clone()) {
-                    eprintln!("Error handling client: {}", e);
-                }
+                let tls_config = tls_config.clone();
+                let http2_pool = http2_pool.clone();
+                pool.execute(move || {
+                    if let Err(e) = proxy_handler::handle_client(client_stream, tls_config, http2_pool) {
+                        eprintln!("Error handling client: {}", e);
+                    }
+                });
             }
             Err(e) => eprintln!("Connection failed: {}", e),
         }